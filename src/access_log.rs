@@ -0,0 +1,70 @@
+use std::fs::{File, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use tracing::warn;
+
+/// Options controlling a [`FileLogger`]'s rotation behavior.
+pub struct FileLogOptions {
+    /// Rotate once the file exceeds this many bytes; `None` disables rotation.
+    pub max_size: Option<u64>,
+}
+
+impl Default for FileLogOptions {
+    fn default() -> Self {
+        Self { max_size: Some(200 * 1024 * 1024) }
+    }
+}
+
+/// Appends structured access-log lines to a file, rotating the current file
+/// to `<path>.1` (overwriting any previous backup) once it grows past
+/// `options.max_size`. Modeled on proxmox-rest-server's `FileLogger`, pared
+/// down to what the proxy needs: a single open handle and one backup
+/// generation, independent of the `tracing`/`RUST_LOG` pipeline.
+pub struct FileLogger {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    options: FileLogOptions,
+}
+
+impl FileLogger {
+    pub fn new(path: impl Into<PathBuf>, options: FileLogOptions) -> io::Result<Self> {
+        let path = path.into();
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self { path, file, written, options })
+    }
+
+    /// Writes `line` terminated by a newline, rotating first if the file has
+    /// already grown past `max_size`. Write/rotate failures are logged via
+    /// `tracing` rather than propagated, so a full disk or bad path can't
+    /// take the proxy down.
+    pub fn log(&mut self, line: &str) {
+        if let Some(max_size) = self.options.max_size {
+            if self.written > max_size {
+                if let Err(e) = self.rotate() {
+                    warn!(err = %e, path = %self.path.display(), "access log rotation failed");
+                }
+            }
+        }
+
+        match writeln!(self.file, "{line}") {
+            Ok(()) => self.written += line.len() as u64 + 1,
+            Err(e) => warn!(err = %e, path = %self.path.display(), "access log write failed"),
+        }
+    }
+
+    fn rotate(&mut self) -> io::Result<()> {
+        std::fs::rename(&self.path, backup_path(&self.path))?;
+        self.file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+fn backup_path(path: &Path) -> PathBuf {
+    let mut backup = path.as_os_str().to_os_string();
+    backup.push(".1");
+    PathBuf::from(backup)
+}
@@ -0,0 +1,194 @@
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine as _};
+use reqwest::Client;
+use serde::Deserialize;
+use tokio::sync::{Mutex, RwLock};
+use tracing::{info, warn};
+
+/// Produces the `Authorization` header value to attach to each upstream
+/// request. Selected at startup via `AUTH_MODE` so the proxy can sit in
+/// front of deployments that don't speak OIDC client_credentials.
+#[async_trait]
+pub trait AuthBackend: Send + Sync {
+    async fn authorization_header(&self) -> Result<String, String>;
+
+    /// Drops any cached credential so the next `authorization_header` call
+    /// mints a fresh one. Backends with nothing to cache can leave this as
+    /// a no-op.
+    async fn invalidate(&self) {}
+}
+
+#[derive(Clone)]
+struct CachedToken {
+    header_value: String, // "Bearer <access_token>"
+    valid_until: Instant,
+}
+
+#[derive(Deserialize)]
+struct TokenResp {
+    access_token: String,
+    expires_in: u64,
+}
+
+/// The original OIDC `client_credentials` grant, with the cached-token
+/// refresh logic that used to live directly on `AppState`.
+pub struct OidcClientCredentials {
+    http: Client,
+    token_url: String,
+    client_id: String,
+    client_secret: String,
+    token: RwLock<Option<CachedToken>>,
+    refresh_lock: Mutex<()>,
+    leeway: Duration,
+}
+
+impl OidcClientCredentials {
+    pub fn new(http: Client, token_url: String, client_id: String, client_secret: String) -> Self {
+        Self {
+            http,
+            token_url,
+            client_id,
+            client_secret,
+            token: RwLock::new(None),
+            refresh_lock: Mutex::new(()),
+            leeway: Duration::from_secs(20),
+        }
+    }
+}
+
+#[async_trait]
+impl AuthBackend for OidcClientCredentials {
+    async fn authorization_header(&self) -> Result<String, String> {
+        if let Some(t) = self.token.read().await.as_ref() {
+            if Instant::now() + self.leeway < t.valid_until {
+                return Ok(t.header_value.clone());
+            }
+        }
+
+        let _g = self.refresh_lock.lock().await;
+
+        if let Some(t) = self.token.read().await.as_ref() {
+            if Instant::now() + self.leeway < t.valid_until {
+                return Ok(t.header_value.clone());
+            }
+        }
+
+        let form = [
+            ("grant_type", "client_credentials"),
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.as_str()),
+            ("scope", "openid"),
+        ];
+
+        let t0 = Instant::now();
+        let resp = self.http.post(&self.token_url).form(&form).send().await;
+        match resp {
+            Ok(r) if r.status().is_success() => {
+                let status = r.status();
+                let tr: TokenResp = r.json().await.map_err(|e| {
+                    warn!(err = %e, "token json parse failed");
+                    format!("token json {e}")
+                })?;
+                let ttl = tr.expires_in.max(60);
+                let valid_until = Instant::now() + Duration::from_secs(ttl.saturating_sub(10));
+                let header_value = format!("Bearer {}", tr.access_token);
+
+                *self.token.write().await = Some(CachedToken {
+                    header_value: header_value.clone(),
+                    valid_until,
+                });
+
+                info!(
+                    took_ms = %t0.elapsed().as_millis(),
+                    status = %status,
+                    expires_in_s = tr.expires_in,
+                    "esplora token refreshed"
+                );
+                Ok(header_value)
+            }
+            Ok(r) => {
+                warn!(
+                    took_ms = %t0.elapsed().as_millis(),
+                    status = %r.status(),
+                    "token refresh http failure"
+                );
+                Err(format!("token status {}", r.status()))
+            }
+            Err(e) => {
+                warn!(took_ms = %t0.elapsed().as_millis(), err = %e, "token refresh request failed");
+                Err(format!("token http err: {e}"))
+            }
+        }
+    }
+
+    async fn invalidate(&self) {
+        *self.token.write().await = None;
+    }
+}
+
+/// Injects a fixed, pre-minted bearer token. No refresh loop, no upstream
+/// calls — useful when credentials are rotated out-of-band.
+pub struct StaticBearer {
+    header_value: String,
+}
+
+impl StaticBearer {
+    pub fn new(token: String) -> Self {
+        Self { header_value: format!("Bearer {token}") }
+    }
+}
+
+#[async_trait]
+impl AuthBackend for StaticBearer {
+    async fn authorization_header(&self) -> Result<String, String> {
+        Ok(self.header_value.clone())
+    }
+}
+
+/// HTTP Basic auth, base64-encoding `user:pass` once at construction.
+pub struct BasicAuth {
+    header_value: String,
+}
+
+impl BasicAuth {
+    pub fn new(user: String, pass: String) -> Self {
+        let encoded = STANDARD.encode(format!("{user}:{pass}"));
+        Self { header_value: format!("Basic {encoded}") }
+    }
+}
+
+#[async_trait]
+impl AuthBackend for BasicAuth {
+    async fn authorization_header(&self) -> Result<String, String> {
+        Ok(self.header_value.clone())
+    }
+}
+
+/// Builds the configured `AuthBackend` from the environment. `AUTH_MODE`
+/// selects the implementation; defaults to `oidc` to match prior behavior.
+pub fn from_env(http: Client) -> Arc<dyn AuthBackend> {
+    let mode = std::env::var("AUTH_MODE").unwrap_or_else(|_| "oidc".to_string());
+    match mode.as_str() {
+        "static" => {
+            let token = std::env::var("STATIC_BEARER_TOKEN").expect("STATIC_BEARER_TOKEN missing");
+            Arc::new(StaticBearer::new(token))
+        }
+        "basic" => {
+            let user = std::env::var("BASIC_AUTH_USER").expect("BASIC_AUTH_USER missing");
+            let pass = std::env::var("BASIC_AUTH_PASS").expect("BASIC_AUTH_PASS missing");
+            Arc::new(BasicAuth::new(user, pass))
+        }
+        "oidc" => {
+            let token_url = std::env::var("OIDC_TOKEN_URL").unwrap_or_else(|_| {
+                "https://login.blockstream.com/realms/blockstream-public/protocol/openid-connect/token".to_string()
+            });
+            let client_id = std::env::var("ESPLORA_CLIENT_ID").expect("ESPLORA_CLIENT_ID missing");
+            let client_secret = std::env::var("ESPLORA_CLIENT_SECRET").expect("ESPLORA_CLIENT_SECRET missing");
+            Arc::new(OidcClientCredentials::new(http, token_url, client_id, client_secret))
+        }
+        other => panic!("unknown AUTH_MODE {other:?}, expected oidc|static|basic"),
+    }
+}
@@ -0,0 +1,85 @@
+use std::num::NonZeroUsize;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use axum::http::Method;
+use bytes::Bytes;
+use lru::LruCache;
+
+/// A cached upstream response: enough to replay `status`/`headers`/`body`
+/// verbatim on a hit.
+#[derive(Clone)]
+pub struct CacheEntry {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Bytes,
+    pub expires_at: Instant,
+}
+
+/// In-memory LRU cache for immutable Esplora GETs (confirmed blocks/txs),
+/// keyed by `METHOD path?query`. Bounded by entry count (`CACHE_CAPACITY`)
+/// and per-entry size (`CACHE_MAX_BYTES`) so a handful of large responses
+/// can't blow the cache out.
+pub struct ResponseCache {
+    entries: Mutex<LruCache<String, CacheEntry>>,
+    max_entry_bytes: usize,
+    default_ttl: Duration,
+    immutable_patterns: Vec<String>,
+}
+
+impl ResponseCache {
+    pub fn new(capacity: usize, max_entry_bytes: usize, default_ttl: Duration, immutable_patterns: Vec<String>) -> Self {
+        let capacity = NonZeroUsize::new(capacity).unwrap_or(NonZeroUsize::new(1).unwrap());
+        Self {
+            entries: Mutex::new(LruCache::new(capacity)),
+            max_entry_bytes,
+            default_ttl,
+            immutable_patterns,
+        }
+    }
+
+    pub fn default_ttl(&self) -> Duration {
+        self.default_ttl
+    }
+
+    pub fn max_entry_bytes(&self) -> usize {
+        self.max_entry_bytes
+    }
+
+    /// Whether `method`+`path` falls in the configured allowlist of
+    /// immutable-once-confirmed Esplora endpoints.
+    pub fn is_cacheable_path(&self, method: &Method, path: &str) -> bool {
+        method == Method::GET && self.immutable_patterns.iter().any(|p| glob_match(p, path))
+    }
+
+    pub fn key(method: &Method, path: &str, query: Option<&str>) -> String {
+        format!("{method} {path}?{}", query.unwrap_or(""))
+    }
+
+    pub fn get(&self, key: &str) -> Option<CacheEntry> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(entry) if entry.expires_at > Instant::now() => Some(entry.clone()),
+            Some(_) => {
+                entries.pop(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub fn insert(&self, key: String, entry: CacheEntry) {
+        if entry.body.len() > self.max_entry_bytes {
+            return;
+        }
+        self.entries.lock().unwrap().put(key, entry);
+    }
+}
+
+/// Matches `path` against `pattern`, where a `*` path segment in `pattern`
+/// matches any single segment in `path` (e.g. `/block/*/raw`).
+fn glob_match(pattern: &str, path: &str) -> bool {
+    let p = pattern.split('/');
+    let s = path.split('/');
+    p.clone().count() == s.clone().count() && p.zip(s).all(|(p, s)| p == "*" || p == s)
+}
@@ -0,0 +1,111 @@
+use std::io;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+use axum::serve::Listener;
+use tokio::io::AsyncReadExt;
+use tokio::net::{TcpListener, TcpStream};
+use tracing::{debug, warn};
+
+/// Max PROXY protocol v2 header size: the 16-byte fixed header plus up to
+/// 65535 bytes of address block/TLVs. v1 headers are a single line capped
+/// at 107 bytes, well within this bound too.
+const MAX_PROXY_HEADER_BYTES: usize = 16 + 65535;
+
+/// How long to wait for a full PROXY header to arrive before concluding
+/// there isn't one. Only paid on connections accepted with
+/// `PROXY_PROTOCOL=1`, where every peer is expected to send one.
+const PROXY_HEADER_DEADLINE: Duration = Duration::from_millis(500);
+
+/// Wraps a `TcpListener`, optionally peeling a PROXY protocol v1/v2 header
+/// (`PROXY_PROTOCOL=1`) off each accepted connection to recover the real
+/// client address from behind a TCP load balancer. Falls back to the
+/// socket's own peer address when the flag is off or no header is present.
+pub struct ProxyProtocolListener {
+    inner: TcpListener,
+    enabled: bool,
+}
+
+impl ProxyProtocolListener {
+    pub fn new(inner: TcpListener, enabled: bool) -> Self {
+        Self { inner, enabled }
+    }
+}
+
+impl Listener for ProxyProtocolListener {
+    type Io = TcpStream;
+    type Addr = SocketAddr;
+
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        loop {
+            let (mut stream, peer) = match self.inner.accept().await {
+                Ok(pair) => pair,
+                Err(e) => {
+                    warn!(err = %e, "accept failed");
+                    continue;
+                }
+            };
+
+            if !self.enabled {
+                return (stream, peer);
+            }
+
+            match read_proxy_header(&mut stream).await {
+                Ok(Some(real)) => {
+                    debug!(peer = %peer, real_client = %real, "recovered client address via PROXY protocol");
+                    return (stream, real);
+                }
+                Ok(None) => return (stream, peer),
+                Err(e) => {
+                    warn!(peer = %peer, err = %e, "PROXY protocol parse failed, using socket peer addr");
+                    return (stream, peer);
+                }
+            }
+        }
+    }
+
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        self.inner.local_addr()
+    }
+}
+
+/// Peeks the header bytes of a freshly accepted connection and, if they
+/// parse as a PROXY protocol v1 or v2 preamble, consumes exactly that many
+/// bytes and returns the original source address it carried.
+///
+/// A single short peek isn't enough: a v2 header's address block/TLVs can
+/// arrive in a second TCP segment, and `peek` only sees what the kernel has
+/// buffered so far. So this grows the peek buffer (up to
+/// `MAX_PROXY_HEADER_BYTES`) and retries until parsing succeeds or
+/// `PROXY_HEADER_DEADLINE`, counted from the first peek, elapses — only then
+/// is it treated as "no header present" rather than risking the partial
+/// preamble being parsed as the start of the HTTP request line.
+async fn read_proxy_header(stream: &mut TcpStream) -> io::Result<Option<SocketAddr>> {
+    let mut buf = vec![0u8; 256];
+    let deadline = Instant::now() + PROXY_HEADER_DEADLINE;
+
+    loop {
+        let n = stream.peek(&mut buf).await?;
+        if n > 0 {
+            let mut cursor = std::io::Cursor::new(&buf[..n]);
+            if let Ok(header) = proxy_protocol::parse(&mut cursor) {
+                let consumed = cursor.position() as usize;
+                let mut discard = vec![0u8; consumed];
+                stream.read_exact(&mut discard).await?;
+                return Ok(header.proxied_address().map(|a| a.source));
+            }
+        }
+
+        // parse failed (or nothing has arrived yet): the header may simply
+        // not be fully buffered yet. Grow the peek window so a full v2
+        // header still fits, then wait for more bytes before giving up.
+        if n == buf.len() && buf.len() < MAX_PROXY_HEADER_BYTES {
+            buf.resize((buf.len() * 4).min(MAX_PROXY_HEADER_BYTES), 0);
+            continue;
+        }
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+}
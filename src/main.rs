@@ -1,111 +1,282 @@
+mod access_log;
+mod auth;
+mod cache;
+mod conn;
+
 use axum::{
     body::Body,
-    extract::{OriginalUri, State},
+    extract::{connect_info::ConnectInfo, OriginalUri, State},
     http::{HeaderMap, HeaderValue, Method, StatusCode},
     response::IntoResponse,
     routing::any,
     Router,
 };
+use access_log::FileLogger;
+use auth::AuthBackend;
+use cache::{CacheEntry, ResponseCache};
+use conn::ProxyProtocolListener;
+use futures_util::Stream;
 use reqwest::Client;
-use serde::Deserialize;
-use std::{collections::HashMap, env, sync::Arc, time::{Duration, Instant}};
+use std::{
+    collections::HashMap, env, net::SocketAddr, pin::Pin, sync::{Arc, Mutex},
+    task::{Context, Poll},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
 use tokio::net::TcpListener;
-use tokio::sync::{Mutex, RwLock};
-use http_body_util::BodyExt as _;   // <- important
 use bytes::Bytes;
 use hex;
-use tracing::{debug, info, warn};
+use tracing::{debug, warn};
 use dotenvy::dotenv;
 #[derive(Clone)]
 struct AppState {
     http: Client,
     upstream_base: String,     // e.g. https://enterprise.blockstream.info/api
-    token_url: String,         // OIDC token endpoint
-    client_id: String,
-    client_secret: String,
-    // (optional) shared secret your app sends via set_chain_source_esplora_with_headers
-    token: Arc<RwLock<Option<CachedToken>>>,
-    refresh_lock: Arc<Mutex<()>>,
-    leeway: Duration,          // refresh a bit before expiry
+    auth: Arc<dyn AuthBackend>,
+    max_uri_len: usize,        // MAX_URI_LEN
+    max_query_len: usize,      // MAX_QUERY_LEN
+    max_body_bytes: u64,       // MAX_BODY_BYTES
+    cache: Arc<ResponseCache>,
+    access_log: Option<Arc<Mutex<FileLogger>>>, // ACCESS_LOG
 }
 
-#[derive(Clone)]
-struct CachedToken {
-    header_value: String,      // "Bearer <access_token>"
-    valid_until: Instant,
+/// Formats one structured access-log line: `ts` is milliseconds since the
+/// Unix epoch, `path` includes the query string, `bytes` the response body
+/// length, and `took_ms` the whole request's wall-clock time (cache hits
+/// included).
+fn access_log_line(method: &Method, path: &str, status: StatusCode, bytes: usize, took_ms: u128) -> String {
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis())
+        .unwrap_or(0);
+    format!("ts={ts} method={method} path={path} status={status} bytes={bytes} took_ms={took_ms}")
 }
 
-#[derive(Deserialize)]
-struct TokenResp {
-    access_token: String,
-    expires_in: u64,
+
+/// Accumulates a bounded copy of the response body as it streams past, to
+/// populate the response cache once the upstream stream completes
+/// successfully. Capped at the cache's `max_entry_bytes()`; overflowing just drops the fill
+/// without aborting the stream (the cache only wants small bodies anyway).
+struct CacheFill {
+    store: Arc<ResponseCache>,
+    key: String,
+    status: u16,
+    headers: Vec<(String, String)>,
+    ttl: Duration,
+    buf: Vec<u8>,
+    overflowed: bool,
 }
 
-impl AppState {
-    async fn bearer(&self) -> Result<String, String> {
-        if let Some(t) = self.token.read().await.as_ref() {
-            if Instant::now() + self.leeway < t.valid_until {
-                return Ok(t.header_value.clone());
+/// Wraps a response byte stream, teeing the first `dump_n` bytes into a
+/// buffer for the `DUMP_BODY_BYTES` debug preview and (optionally) the
+/// whole body into a `CacheFill`, while passing every chunk through to the
+/// client unmodified. Logs the preview once the upstream stream completes
+/// or errors; populates the cache only on a clean completion.
+struct PreviewTee<S> {
+    inner: S,
+    status: StatusCode,
+    t0: Instant,
+    dump_n: usize,
+    preview: Vec<u8>,
+    remaining: usize,
+    len: usize,
+    resp_headers: Vec<(String, String)>,
+    logged: bool,
+    cache_fill: Option<CacheFill>,
+    method: Method,
+    path: String,
+    req_t0: Instant,
+    access_log: Option<Arc<Mutex<FileLogger>>>,
+}
+
+impl<S> PreviewTee<S> {
+    fn finish(&mut self, success: bool) {
+        if self.logged {
+            return;
+        }
+        self.logged = true;
+        if self.dump_n > 0 {
+            if let Ok(s) = std::str::from_utf8(&self.preview) {
+                debug!(status=%self.status, len=self.len, took_ms=%self.t0.elapsed().as_millis(), preview=?s, "proxy response");
+            } else {
+                debug!(status=%self.status, len=self.len, took_ms=%self.t0.elapsed().as_millis(), preview_hex=%hex::encode(&self.preview), "proxy response");
             }
+        } else {
+            debug!(
+                status = %self.status,
+                len = self.len,
+                took_ms = %self.t0.elapsed().as_millis(),
+                resp_headers = ?self.resp_headers,
+                "proxy response"
+            );
         }
 
-        let _g = self.refresh_lock.lock().await;
+        if let Some(fill) = self.cache_fill.take() {
+            if success && !fill.overflowed {
+                fill.store.insert(
+                    fill.key,
+                    CacheEntry {
+                        status: fill.status,
+                        headers: fill.headers,
+                        body: Bytes::from(fill.buf),
+                        expires_at: Instant::now() + fill.ttl,
+                    },
+                );
+            }
+        }
 
-        if let Some(t) = self.token.read().await.as_ref() {
-            if Instant::now() + self.leeway < t.valid_until {
-                return Ok(t.header_value.clone());
+        if let Some(log) = &self.access_log {
+            let line = access_log_line(&self.method, &self.path, self.status, self.len, self.req_t0.elapsed().as_millis());
+            if let Ok(mut logger) = log.lock() {
+                logger.log(&line);
             }
         }
+    }
+}
 
-        let form = [
-            ("grant_type", "client_credentials"),
-            ("client_id", self.client_id.as_str()),
-            ("client_secret", self.client_secret.as_str()),
-            ("scope", "openid"),
-        ];
-
-        let t0 = Instant::now();
-        let resp = self.http.post(&self.token_url).form(&form).send().await;
-        match resp {
-            Ok(r) if r.status().is_success() => {
-                let status = r.status();
-                let tr: TokenResp = r.json().await.map_err(|e| {
-                    warn!(err = %e, "token json parse failed");
-                    format!("token json {e}")
-                })?;
-                let ttl = tr.expires_in.max(60);
-                let valid_until = Instant::now() + Duration::from_secs(ttl.saturating_sub(10));
-                let header_value = format!("Bearer {}", tr.access_token);
-
-                *self.token.write().await = Some(CachedToken {
-                    header_value: header_value.clone(),
-                    valid_until,
-                });
-
-                info!(
-                    took_ms = %t0.elapsed().as_millis(),
-                    status = %status,
-                    expires_in_s = tr.expires_in,
-                    "esplora token refreshed"
-                );
-                Ok(header_value)
+impl<S> Stream for PreviewTee<S>
+where
+    S: Stream<Item = reqwest::Result<Bytes>> + Unpin,
+{
+    type Item = Result<Bytes, std::io::Error>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match Pin::new(&mut self.inner).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.len += chunk.len();
+                if self.remaining > 0 {
+                    let take = self.remaining.min(chunk.len());
+                    self.preview.extend_from_slice(&chunk[..take]);
+                    self.remaining -= take;
+                }
+                if let Some(fill) = self.cache_fill.as_mut() {
+                    if !fill.overflowed {
+                        if fill.buf.len() + chunk.len() > fill.store.max_entry_bytes() {
+                            fill.overflowed = true;
+                            fill.buf.clear();
+                            fill.buf.shrink_to_fit();
+                        } else {
+                            fill.buf.extend_from_slice(&chunk);
+                        }
+                    }
+                }
+                Poll::Ready(Some(Ok(chunk)))
             }
-            Ok(r) => {
-                warn!(
-                    took_ms = %t0.elapsed().as_millis(),
-                    status = %r.status(),
-                    "token refresh http failure"
-                );
-                Err(format!("token status {}", r.status()))
+            Poll::Ready(Some(Err(e))) => {
+                self.finish(false);
+                Poll::Ready(Some(Err(std::io::Error::new(std::io::ErrorKind::Other, e))))
             }
-            Err(e) => {
-                warn!(took_ms = %t0.elapsed().as_millis(), err = %e, "token refresh request failed");
-                Err(format!("token http err: {e}"))
+            Poll::Ready(None) => {
+                self.finish(true);
+                Poll::Ready(None)
             }
+            Poll::Pending => Poll::Pending,
         }
     }
 }
 
+/// Error yielded once a request body stream exceeds `MAX_BODY_BYTES`.
+#[derive(Debug)]
+struct BodyTooLarge;
+
+impl std::fmt::Display for BodyTooLarge {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "request body exceeded MAX_BODY_BYTES")
+    }
+}
+
+impl std::error::Error for BodyTooLarge {}
+
+/// Whether `e` (or anything in its `source()` chain) is our own
+/// `BodyTooLarge`, i.e. the request body overran `MAX_BODY_BYTES` mid-stream
+/// rather than a genuine upstream connection failure.
+fn is_body_too_large(e: &reqwest::Error) -> bool {
+    let mut cur: &(dyn std::error::Error + 'static) = e;
+    loop {
+        if cur.downcast_ref::<BodyTooLarge>().is_some() {
+            return true;
+        }
+        match cur.source() {
+            Some(next) => cur = next,
+            None => return false,
+        }
+    }
+}
+
+/// Caps the total bytes pulled from an incoming request body stream,
+/// erroring once `limit` is exceeded instead of forwarding an unbounded
+/// body upstream. A `Content-Length` check in `proxy` rejects the common
+/// case up front; this guards clients that lie about or omit it.
+struct LimitedBodyStream<S> {
+    inner: S,
+    limit: u64,
+    seen: u64,
+}
+
+impl<S> Stream for LimitedBodyStream<S>
+where
+    S: Stream<Item = Result<Bytes, axum::Error>> + Unpin,
+{
+    type Item = Result<Bytes, axum::BoxError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match Pin::new(&mut self.inner).poll_next(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.seen += chunk.len() as u64;
+                if self.seen > self.limit {
+                    return Poll::Ready(Some(Err(Box::new(BodyTooLarge))));
+                }
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(Box::new(e)))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+fn env_parsed<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key).ok().and_then(|s| s.parse().ok()).unwrap_or(default)
+}
+
+/// Outgoing headers for the upstream request: strip hop-by-hop headers and
+/// any incoming auth, stamp the freshly minted bearer/basic header, and
+/// tell the upstream who the real caller is (appending to any existing
+/// `X-Forwarded-For` rather than trusting it outright).
+fn outgoing_headers(headers: &HeaderMap, bearer: &str, client_addr: SocketAddr) -> HeaderMap {
+    let mut out = HeaderMap::new();
+    for (k, v) in headers.iter() {
+        let n = k.as_str().to_ascii_lowercase();
+        if matches!(n.as_str(),
+            "connection"|"keep-alive"|"proxy-authenticate"|"proxy-authorization"|
+            "te"|"trailer"|"transfer-encoding"|"upgrade"|"authorization"|"host"
+        ) { continue; }
+        out.append(k, v.clone());
+    }
+    out.insert("authorization", HeaderValue::from_str(bearer).unwrap());
+
+    let client_ip = client_addr.ip();
+    let xff = match headers.get("x-forwarded-for").and_then(|v| v.to_str().ok()) {
+        Some(existing) => format!("{existing}, {client_ip}"),
+        None => client_ip.to_string(),
+    };
+    out.insert("x-forwarded-for", HeaderValue::from_str(&xff).unwrap());
+    out.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+    out.insert(
+        "forwarded",
+        HeaderValue::from_str(&format!("for={};proto=https", forwarded_for_token(client_ip))).unwrap(),
+    );
+    out
+}
+
+/// Renders an IP for RFC 7239 `Forwarded`'s `for=` token: IPv6 addresses
+/// must be bracketed and quoted (`"[::1]"`), IPv4 stays bare. Unlike
+/// `X-Forwarded-For`, an unbracketed IPv6 address here is malformed.
+fn forwarded_for_token(ip: std::net::IpAddr) -> String {
+    match ip {
+        std::net::IpAddr::V6(_) => format!("\"[{ip}]\""),
+        std::net::IpAddr::V4(_) => ip.to_string(),
+    }
+}
 
 fn redact_headers(h: &HeaderMap) -> Vec<(String, String)> {
     h.iter()
@@ -124,11 +295,21 @@ fn redact_headers(h: &HeaderMap) -> Vec<(String, String)> {
 
 async fn proxy(
     State(st): State<AppState>,
+    ConnectInfo(client_addr): ConnectInfo<SocketAddr>,
     method: Method,
     headers: HeaderMap,
     OriginalUri(orig): OriginalUri,
     body: Body,
 ) -> Result<impl IntoResponse, (StatusCode, String)> {
+    let req_t0 = Instant::now();
+    // reject absurdly long URIs/queries before doing any work
+    if orig.path().len() > st.max_uri_len {
+        return Err((StatusCode::URI_TOO_LONG, format!("path exceeds {} bytes", st.max_uri_len)));
+    }
+    if orig.query().map(str::len).unwrap_or(0) > st.max_query_len {
+        return Err((StatusCode::URI_TOO_LONG, format!("query exceeds {} bytes", st.max_query_len)));
+    }
+
     // build upstream URL
     let mut pathq = orig.path().to_string();
     if let Some(q) = orig.query() { pathq.push('?'); pathq.push_str(q); }
@@ -144,35 +325,101 @@ async fn proxy(
         "proxy request"
     );
 
-    // read request body (rarely used by Esplora; still handle it)
-    let req_bytes: Bytes = body
-        .collect().await
-        .map_err(|e| (StatusCode::BAD_REQUEST, e.to_string()))?
-        .to_bytes();
+    // serve immutable GETs straight from cache when present
+    let cache_key = ResponseCache::key(&method, orig.path(), orig.query());
+    let cacheable = st.cache.is_cacheable_path(&method, orig.path());
+    if cacheable {
+        if let Some(entry) = st.cache.get(&cache_key) {
+            let mut hit_headers = HeaderMap::new();
+            for (k, v) in &entry.headers {
+                if let (Ok(name), Ok(val)) = (
+                    axum::http::HeaderName::try_from(k.as_str()),
+                    HeaderValue::from_str(v),
+                ) {
+                    hit_headers.append(name, val);
+                }
+            }
+            hit_headers.insert("x-cache", HeaderValue::from_static("HIT"));
+            let status = StatusCode::from_u16(entry.status).unwrap_or(StatusCode::OK);
+            if let Some(log) = &st.access_log {
+                let line = access_log_line(&method, &pathq, status, entry.body.len(), req_t0.elapsed().as_millis());
+                if let Ok(mut logger) = log.lock() {
+                    logger.log(&line);
+                }
+            }
+            return Ok((status, hit_headers, Body::from(entry.body)).into_response());
+        }
+    }
+
+    // fast-path reject on a declared Content-Length; the stream limiter
+    // below still guards clients that lie about or omit it.
+    let content_length = headers
+        .get(axum::http::header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|s| s.parse::<u64>().ok());
+    if let Some(len) = content_length {
+        if len > st.max_body_bytes {
+            return Err((StatusCode::PAYLOAD_TOO_LARGE, format!("body exceeds {} bytes", st.max_body_bytes)));
+        }
+    }
+
+    // Esplora's surface is overwhelmingly bodyless GETs; only attach a
+    // request body (and pay for `wrap_stream`'s Transfer-Encoding: chunked)
+    // when one is actually declared, by a non-zero Content-Length or a
+    // Transfer-Encoding. Forwarding a chunked body on GET/HEAD is
+    // non-standard and some upstreams reject it outright.
+    let has_body = !matches!(method, Method::GET | Method::HEAD)
+        && (content_length.unwrap_or(0) > 0
+            || headers.contains_key(axum::http::header::TRANSFER_ENCODING));
 
     // get/refresh token
-    let bearer = st.bearer().await.map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+    let bearer = st.auth.authorization_header().await.map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+    let out = outgoing_headers(&headers, &bearer, client_addr);
 
-    // outgoing headers (strip hop-by-hop & incoming auth)
-    let mut out = HeaderMap::new();
-    for (k, v) in headers.iter() {
-        let n = k.as_str().to_ascii_lowercase();
-        if matches!(n.as_str(),
-            "connection"|"keep-alive"|"proxy-authenticate"|"proxy-authorization"|
-            "te"|"trailer"|"transfer-encoding"|"upgrade"|"authorization"|"host"
-        ) { continue; }
-        out.append(k, v.clone());
+    let t0 = Instant::now();
+    let mut req = st.http.request(method.clone(), &upstream).headers(out);
+    if has_body {
+        // stream the request body straight through to reqwest rather than
+        // buffering it, capped at MAX_BODY_BYTES.
+        let limited = LimitedBodyStream {
+            inner: body.into_data_stream(),
+            limit: st.max_body_bytes,
+            seen: 0,
+        };
+        req = req.body(reqwest::Body::wrap_stream(limited));
     }
-    out.insert("authorization", HeaderValue::from_str(&bearer).unwrap());
+    let mut resp = req.send().await.map_err(|e| {
+        if is_body_too_large(&e) {
+            (StatusCode::PAYLOAD_TOO_LARGE, format!("body exceeds {} bytes", st.max_body_bytes))
+        } else {
+            (StatusCode::BAD_GATEWAY, e.to_string())
+        }
+    })?;
 
-    let t0 = Instant::now();
-    let resp = st.http
-        .request(method.clone(), &upstream)
-        .headers(out)
-        .body(req_bytes)
-        .send()
-        .await
-        .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+    // the cached token can go stale if the upstream rotates/revokes keys
+    // out from under us; force a refresh and replay once. Only safe to
+    // replay bodyless requests, since the original stream is now consumed.
+    let retryable = matches!(method, Method::GET | Method::HEAD);
+    if matches!(resp.status().as_u16(), 401 | 403) {
+        if retryable {
+            warn!(status = %resp.status(), upstream = %upstream, "upstream rejected credentials, invalidating cached token and retrying once");
+            st.auth.invalidate().await;
+            let bearer = st.auth.authorization_header().await.map_err(|e| (StatusCode::BAD_GATEWAY, e))?;
+            let out = outgoing_headers(&headers, &bearer, client_addr);
+            resp = st.http
+                .request(method.clone(), &upstream)
+                .headers(out)
+                .send()
+                .await
+                .map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
+        } else {
+            // body already consumed, so no safe replay here — still drop the
+            // cached token so the next request mints a fresh one instead of
+            // failing for the rest of the refresh window.
+            warn!(status = %resp.status(), upstream = %upstream, "upstream rejected credentials on a non-retryable request, invalidating cached token");
+            st.auth.invalidate().await;
+        }
+    }
 
     let status = StatusCode::from_u16(resp.status().as_u16()).unwrap();
     let mut resp_headers = HeaderMap::new();
@@ -185,34 +432,82 @@ async fn proxy(
         resp_headers.append(k, v.clone());
     }
 
-    // optionally dump some of the body for debugging
+    // optionally dump some of the body for debugging; tee only the first
+    // N bytes off the stream instead of collecting the whole response.
     let dump_n: usize = std::env::var("DUMP_BODY_BYTES")
         .ok()
         .and_then(|s| s.parse().ok())
         .unwrap_or(0);
 
-    let body_bytes = resp.bytes().await.map_err(|e| (StatusCode::BAD_GATEWAY, e.to_string()))?;
-    let len = body_bytes.len();
-    if dump_n > 0 {
-        let take = dump_n.min(len);
-        let preview = &body_bytes[..take];
-        // try to log as UTF-8; if binary, show hex of first few bytes
-        if let Ok(s) = std::str::from_utf8(preview) {
-            debug!(status=%status, len=len, took_ms=%t0.elapsed().as_millis(), preview=?s, "proxy response");
-        } else {
-            debug!(status=%status, len=len, took_ms=%t0.elapsed().as_millis(), preview_hex=%hex::encode(preview), "proxy response");
-        }
+    // populate the cache on a successful, cacheable response; respect an
+    // upstream Cache-Control max-age when present, else fall back to the
+    // configured default (these endpoints only vary once confirmed). A
+    // no-store/no-cache/private directive overrides the path allowlist —
+    // don't cache against the upstream's wishes.
+    let cache_fill = if cacheable && status == StatusCode::OK && !cache_control_disallows_store(&resp_headers) {
+        let ttl = cache_control_max_age(&resp_headers).unwrap_or_else(|| st.cache.default_ttl());
+        Some(CacheFill {
+            store: st.cache.clone(),
+            key: cache_key,
+            status: status.as_u16(),
+            headers: owned_headers(&resp_headers),
+            ttl,
+            buf: Vec::new(),
+            overflowed: false,
+        })
     } else {
-        debug!(
-            status = %status,
-            len = len,
-            took_ms = %t0.elapsed().as_millis(),
-            resp_headers = ?redact_headers(&resp_headers),
-            "proxy response"
-        );
+        None
+    };
+
+    if cacheable {
+        resp_headers.insert("x-cache", HeaderValue::from_static("MISS"));
     }
 
-    Ok((status, resp_headers, body_bytes))
+    let redacted_resp_headers = redact_headers(&resp_headers);
+    let stream = PreviewTee {
+        inner: resp.bytes_stream(),
+        status,
+        t0,
+        dump_n,
+        preview: Vec::with_capacity(dump_n),
+        remaining: dump_n,
+        len: 0,
+        resp_headers: redacted_resp_headers,
+        logged: false,
+        cache_fill,
+        method: method.clone(),
+        path: pathq.clone(),
+        req_t0,
+        access_log: st.access_log.clone(),
+    };
+
+    Ok((status, resp_headers, Body::from_stream(stream)).into_response())
+}
+
+fn owned_headers(h: &HeaderMap) -> Vec<(String, String)> {
+    h.iter()
+        .filter_map(|(k, v)| Some((k.as_str().to_string(), v.to_str().ok()?.to_string())))
+        .collect()
+}
+
+/// Parses `max-age=N` out of an upstream `Cache-Control` header, if any.
+fn cache_control_max_age(h: &HeaderMap) -> Option<Duration> {
+    let raw = h.get(axum::http::header::CACHE_CONTROL)?.to_str().ok()?;
+    raw.split(',')
+        .find_map(|part| part.trim().strip_prefix("max-age="))
+        .and_then(|n| n.parse::<u64>().ok())
+        .map(Duration::from_secs)
+}
+
+/// Whether an upstream `Cache-Control` header opts this response out of
+/// caching entirely (`no-store`/`no-cache`/`private`), which overrides the
+/// path allowlist.
+fn cache_control_disallows_store(h: &HeaderMap) -> bool {
+    let Some(raw) = h.get(axum::http::header::CACHE_CONTROL).and_then(|v| v.to_str().ok()) else {
+        return false;
+    };
+    raw.split(',')
+        .any(|part| matches!(part.trim(), "no-store" | "no-cache" | "private"))
 }
 
 #[tokio::main]
@@ -226,35 +521,62 @@ async fn main() {
     // ENV
     let upstream = env::var("ESPLORA_UPSTREAM")
         .unwrap_or_else(|_| "https://enterprise.blockstream.info/api".to_string());
-    let token_url = env::var("OIDC_TOKEN_URL")
-        .unwrap_or_else(|_| "https://login.blockstream.com/realms/blockstream-public/protocol/openid-connect/token".to_string());
-    let client_id     = env::var("ESPLORA_CLIENT_ID").expect("ESPLORA_CLIENT_ID missing");
-    let client_secret = env::var("ESPLORA_CLIENT_SECRET").expect("ESPLORA_CLIENT_SECRET missing");
     let bind = env::var("BIND").unwrap_or_else(|_| "127.0.0.1:3002".to_string());
+    let max_uri_len = env_parsed("MAX_URI_LEN", 2048usize);
+    let max_query_len = env_parsed("MAX_QUERY_LEN", 4096usize);
+    let max_body_bytes = env_parsed("MAX_BODY_BYTES", 10 * 1024 * 1024u64);
+    let cache_capacity = env_parsed("CACHE_CAPACITY", 10_000usize);
+    let cache_max_bytes = env_parsed("CACHE_MAX_BYTES", 2 * 1024 * 1024usize);
+    let cache_ttl_secs = env_parsed("CACHE_TTL_SECS", 3600u64);
+    let cache_patterns = env::var("CACHE_IMMUTABLE_PATTERNS")
+        .map(|s| s.split(',').map(str::to_string).collect())
+        .unwrap_or_else(|_| {
+            [
+                "/block/*", "/block/*/raw", "/block/*/txs/*", "/block/*/txids", "/block/*/header",
+                // not "/tx/*": that's the tx JSON itself, which carries mutable
+                // status.{confirmed,block_height,block_hash} until it's buried;
+                // only its immutable serialized sub-resources are safe to cache.
+                "/tx/*/hex", "/tx/*/raw", "/tx/*/merkle-proof", "/tx/*/merkleblock-proof",
+            ]
+            .into_iter()
+            .map(str::to_string)
+            .collect()
+        });
+    let access_log = env::var("ACCESS_LOG").ok().map(|path| {
+        let logger = FileLogger::new(&path, access_log::FileLogOptions::default())
+            .unwrap_or_else(|e| panic!("failed to open ACCESS_LOG {path}: {e}"));
+        Arc::new(Mutex::new(logger))
+    });
 
-
+    let http = Client::builder()
+        .pool_max_idle_per_host(32)
+        .pool_idle_timeout(Duration::from_secs(45))
+        .gzip(true).brotli(true).deflate(true)
+        .build().unwrap();
 
     let st = AppState {
-        http: Client::builder()
-            .pool_max_idle_per_host(32)
-            .pool_idle_timeout(Duration::from_secs(45))
-            .gzip(true).brotli(true).deflate(true)
-            .build().unwrap(),
+        auth: auth::from_env(http.clone()),
+        http,
         upstream_base: upstream,
-        token_url,
-        client_id,
-        client_secret,
-        token: Arc::new(RwLock::new(None)),
-        refresh_lock: Arc::new(Mutex::new(())),
-        leeway: Duration::from_secs(20),
+        max_uri_len,
+        max_query_len,
+        max_body_bytes,
+        cache: Arc::new(ResponseCache::new(
+            cache_capacity,
+            cache_max_bytes,
+            Duration::from_secs(cache_ttl_secs),
+            cache_patterns,
+        )),
+        access_log,
     };
 
-    // warm token in background & refresh every ~4 min
+    // warm/refresh credentials in background every ~4 min; token-less
+    // backends (static, basic) just no-op here.
     {
         let st2 = st.clone();
         tokio::spawn(async move {
             loop {
-                let _ = st2.bearer().await;
+                let _ = st2.auth.authorization_header().await;
                 tokio::time::sleep(Duration::from_secs(240)).await;
             }
         });
@@ -264,8 +586,15 @@ async fn main() {
         .route("/*path", any(proxy))
         .with_state(st);
 
+    let proxy_protocol = env_parsed("PROXY_PROTOCOL", 0u8) != 0;
+
     println!("esplora_auth_proxy listening on http://{bind}");
     let listener = TcpListener::bind(&bind).await.unwrap();
-    println!("esplora_auth_proxy listening on http://{bind}");
-    axum::serve(listener, app).await.unwrap();
+    let listener = ProxyProtocolListener::new(listener, proxy_protocol);
+    axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .await
+    .unwrap();
 }